@@ -0,0 +1,223 @@
+use std::{collections::HashMap, fs, path::PathBuf, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+use evdev::{BusType, InputId};
+use serde::Deserialize;
+
+use crate::{parse_mappings, parse_source, parse_target, MapSource, Mapping};
+
+/// The emulated device's identity, in place of the hardcoded Xbox 360 pad, and the physical
+/// sources feeding it. Produced either by [`load`]ing a `--config` file or by [`from_args`]
+/// desugaring the legacy `path`/`mappings` CLI flags, so the rest of the program only ever deals
+/// with this one shape.
+pub struct Profile {
+    pub device: VirtualIdentity,
+    pub sources: Vec<SourceConfig>,
+}
+
+/// One physical device selector plus the mapping rules that translate its events.
+pub struct SourceConfig {
+    pub path: PathBuf,
+    pub mappings: HashMap<MapSource, Mapping>,
+}
+
+/// The name and `InputId` the virtual device presents to the kernel.
+#[derive(Debug, Clone)]
+pub struct VirtualIdentity {
+    pub name: String,
+    pub input_id: InputId,
+}
+
+impl Default for VirtualIdentity {
+    fn default() -> Self {
+        Self {
+            name: "Microsoft X-Box 360 pad".to_owned(),
+            input_id: InputId::new(BusType::BUS_USB, 0x45e, 0x28e, 0x101),
+        }
+    }
+}
+
+/// On-disk shape of a `--config` TOML profile, parsed with serde then converted into a
+/// [`Profile`] so the rest of the program never has to deal with raw strings.
+#[derive(Debug, Deserialize)]
+struct ProfileFile {
+    device: Option<DeviceFile>,
+    source: Vec<SourceFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceFile {
+    name: String,
+    /// An `evdev::BusType` variant name, e.g. `BUS_USB` or `BUS_BLUETOOTH`.
+    #[serde(default = "default_bus")]
+    bus: String,
+    vendor: u16,
+    product: u16,
+    #[serde(default)]
+    version: u16,
+}
+
+fn default_bus() -> String {
+    "BUS_USB".to_owned()
+}
+
+#[derive(Debug, Deserialize)]
+struct SourceFile {
+    path: PathBuf,
+    mapping: Vec<RuleFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleFile {
+    source: String,
+    target: String,
+    #[serde(default)]
+    invert: bool,
+    #[serde(default)]
+    deadzone: f32,
+}
+
+impl RuleFile {
+    fn into_rule(self) -> Result<(MapSource, Mapping)> {
+        let source = parse_source(&self.source)?;
+        let target = parse_target(&self.target)?;
+        if !(0.0..1.0).contains(&self.deadzone) {
+            bail!("deadzone must be in [0, 1), got {}", self.deadzone);
+        }
+        Ok((
+            source,
+            Mapping {
+                target,
+                invert: self.invert,
+                deadzone: self.deadzone,
+            },
+        ))
+    }
+}
+
+/// Loads and validates a `--config` profile from `path`.
+pub fn load(path: &std::path::Path) -> Result<Profile> {
+    let text = fs::read_to_string(path).context(format!("reading config {}", path.display()))?;
+    let file: ProfileFile = toml::from_str(&text).context("parsing config")?;
+
+    let device = match file.device {
+        Some(d) => VirtualIdentity {
+            input_id: InputId::new(
+                BusType::from_str(&d.bus).map_err(|_| anyhow::anyhow!("unknown bus {}", d.bus))?,
+                d.vendor,
+                d.product,
+                d.version,
+            ),
+            name: d.name,
+        },
+        None => VirtualIdentity::default(),
+    };
+
+    if file.source.is_empty() {
+        bail!("config has no [[source]] entries");
+    }
+    let sources = file
+        .source
+        .into_iter()
+        .map(|s| {
+            let mappings = s
+                .mapping
+                .into_iter()
+                .map(RuleFile::into_rule)
+                .collect::<Result<HashMap<_, _>>>()?;
+            Ok(SourceConfig {
+                path: s.path,
+                mappings,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Profile { device, sources })
+}
+
+/// Desugars the legacy `path`/`mappings` CLI flags into the same [`Profile`] shape a `--config`
+/// file produces, keeping the hardcoded Xbox 360 pad identity as the default.
+pub fn from_args(paths: Vec<PathBuf>, mapping_strs: Vec<String>) -> Result<Profile> {
+    let sources = paths
+        .into_iter()
+        .zip(mapping_strs)
+        .map(|(path, mappings)| {
+            Ok(SourceConfig {
+                path,
+                mappings: parse_mappings(&mappings).context("")?,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Profile {
+        device: VirtualIdentity::default(),
+        sources,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::load;
+    use crate::{MapSource, MapTarget};
+    use evdev::{AbsoluteAxisCode, BusType};
+    use std::fs;
+
+    fn load_str(name: &str, text: &str) -> anyhow::Result<super::Profile> {
+        let path = std::env::temp_dir().join(format!("hid-impostor-test-{name}.toml"));
+        fs::write(&path, text)?;
+        let result = load(&path);
+        fs::remove_file(&path)?;
+        result
+    }
+
+    #[test]
+    fn should_load_profile_from_toml() {
+        let profile = load_str(
+            "load_profile",
+            r#"
+                [device]
+                name = "Test Pad"
+                bus = "BUS_BLUETOOTH"
+                vendor = 0x1234
+                product = 0x5678
+                version = 1
+
+                [[source]]
+                path = "/dev/input/event0"
+                [[source.mapping]]
+                source = "ABS_Z"
+                target = "ABS_RX"
+                deadzone = 0.1
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(profile.device.name, "Test Pad");
+        assert_eq!(profile.device.input_id.bus_type(), BusType::BUS_BLUETOOTH);
+        assert_eq!(profile.device.input_id.vendor(), 0x1234);
+        assert_eq!(profile.device.input_id.product(), 0x5678);
+        assert_eq!(profile.device.input_id.version(), 1);
+
+        assert_eq!(profile.sources.len(), 1);
+        let source = &profile.sources[0];
+        assert_eq!(source.path, std::path::Path::new("/dev/input/event0"));
+        let mapping = source
+            .mappings
+            .get(&MapSource::AbsoluteAxis(AbsoluteAxisCode::ABS_Z))
+            .unwrap();
+        assert_eq!(
+            mapping.target,
+            MapTarget::AbsoluteAxis(AbsoluteAxisCode::ABS_RX)
+        );
+        assert_eq!(mapping.deadzone, 0.1);
+    }
+
+    #[test]
+    fn should_reject_config_with_no_sources() {
+        let err = match load_str("no_sources", "source = []\n") {
+            Ok(_) => panic!("expected an error"),
+            Err(err) => err,
+        };
+        assert_eq!(err.to_string(), "config has no [[source]] entries");
+    }
+}
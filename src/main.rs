@@ -1,84 +1,339 @@
-use std::{collections::HashMap, fs::canonicalize, path::PathBuf, str::FromStr};
+mod config;
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::{canonicalize, read_dir},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{mpsc, Arc},
+    thread,
+};
 
 use anyhow::{bail, Context, Result};
 use clap::{value_parser, Parser};
+use config::VirtualIdentity;
 use evdev::{
     uinput::{VirtualDevice, VirtualDeviceBuilder},
-    AbsInfo, AbsoluteAxisCode, AbsoluteAxisEvent, AttributeSet, BusType, Device,
+    AbsInfo, AbsoluteAxisCode, AbsoluteAxisEvent, AttributeSet, Device,
     EventSummary::*,
-    InputId, KeyCode, UinputAbsSetup,
+    InputEvent, KeyCode, KeyEvent, RelativeAxisCode, SynchronizationCode, UinputAbsSetup,
 };
+use inotify::{Inotify, WatchMask};
+
+/// Where physical devices show up; watched for replugs so a disconnected source can be reopened.
+const DEV_INPUT: &str = "/dev/input";
 
 #[derive(Parser)]
-#[command(author, version, about = "Create a virtual HID device from a physical HID device", long_about = None)]
+#[command(
+    author,
+    version,
+    about = "Create a virtual HID device from one or more physical HID devices",
+    long_about = None
+)]
 struct Args {
-    #[arg(value_parser = value_parser!(PathBuf))]
+    /// One physical device per source; repeat to build a composite virtual gamepad. Cannot be
+    /// combined with `--config`.
+    #[arg(value_parser = value_parser!(PathBuf), conflicts_with = "config")]
+    path: Vec<PathBuf>,
+    /// One mapping set per `path`, in the same order. Cannot be combined with `--config`.
+    #[arg(short = 'm', long, conflicts_with = "config")]
+    mappings: Vec<String>,
+    /// Load the emulated device identity, sources, and mapping rules from a TOML profile instead
+    /// of `path`/`--mappings`.
+    #[arg(short = 'c', long, value_parser = value_parser!(PathBuf))]
+    config: Option<PathBuf>,
+}
+
+/// A single physical device feeding the composite virtual pad, paired with the mapping set that
+/// translates its events.
+struct Source {
     path: PathBuf,
-    #[arg(short = 'm', long)]
-    mappings: String,
+    device: Device,
+    mappings: HashMap<MapSource, Mapping>,
+}
+
+/// Identifies a physical device independent of the `eventN` node it happens to enumerate as,
+/// since that node changes across replugs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DeviceIdentity {
+    name: String,
+    vendor: u16,
+    product: u16,
+}
+
+impl DeviceIdentity {
+    fn of(device: &Device) -> Self {
+        let id = device.input_id();
+        Self {
+            name: device.name().unwrap_or_default().to_owned(),
+            vendor: id.vendor(),
+            product: id.product(),
+        }
+    }
+}
+
+/// A physical input the device can produce, used as the left-hand side of a mapping rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MapSource {
+    Key(KeyCode),
+    AbsoluteAxis(AbsoluteAxisCode),
+    RelativeAxis(RelativeAxisCode),
+}
+
+/// A gamepad input the virtual device can produce, used as the right-hand side of a mapping rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum MapTarget {
+    Key(KeyCode),
+    AbsoluteAxis(AbsoluteAxisCode),
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 struct Mapping {
-    to_code: u16,
+    target: MapTarget,
     invert: bool,
+    /// Per-axis deadzone fraction in `[0, 1)` applied to `AbsoluteAxis` sources before rescaling.
+    deadzone: f32,
 }
 
 impl Mapping {
-    fn from_abs(code: AbsoluteAxisCode) -> Self {
+    fn identity(code: AbsoluteAxisCode) -> Self {
         Self {
-            to_code: code.0,
+            target: MapTarget::AbsoluteAxis(code),
             invert: false,
+            deadzone: 0.0,
         }
     }
+}
 
-    fn from_abs_inv(code: AbsoluteAxisCode) -> Self {
-        Self {
-            to_code: code.0,
-            invert: true,
-        }
-    }
+/// Normalizes `value` to `[-1, 1]` using `source`'s range, applies a per-axis deadzone `d` so
+/// motion stays continuous at its edge (`sign(n) * (|n| - d) / (1 - d)`), then rescales into
+/// `target`'s range.
+fn calibrate(value: i32, source: &AbsInfo, target: &AbsInfo, deadzone: f32) -> i32 {
+    let src_center = (source.maximum() + source.minimum()) as f32 / 2.0;
+    let src_half = (source.maximum() - source.minimum()) as f32 / 2.0;
+    let n = if src_half == 0.0 {
+        0.0
+    } else {
+        (value as f32 - src_center) / src_half
+    };
+    let n = if n.abs() < deadzone {
+        0.0
+    } else {
+        n.signum() * (n.abs() - deadzone) / (1.0 - deadzone)
+    };
+    let dst_center = (target.maximum() + target.minimum()) as f32 / 2.0;
+    let dst_half = (target.maximum() - target.minimum()) as f32 / 2.0;
+    (dst_center + n * dst_half).round() as i32
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
 
-    let path = canonicalize(&args.path).context(format!("no device {}", args.path.display()))?;
-    println!("input device is {}", path.display());
+    let profile = match &args.config {
+        Some(config_path) => config::load(config_path)?,
+        None => {
+            if args.path.is_empty() {
+                bail!("expected at least one device path, or --config");
+            }
+            if args.path.len() != args.mappings.len() {
+                bail!(
+                    "expected one --mappings per device path, got {} path(s) and {} mapping set(s)",
+                    args.path.len(),
+                    args.mappings.len()
+                );
+            }
+            config::from_args(args.path, args.mappings)?
+        }
+    };
 
-    let mut device = Device::open(path)?;
-    println!("{:?}", device.input_id());
+    let sources = profile
+        .sources
+        .into_iter()
+        .map(|source| {
+            let device = connect(&source.path)?;
+            println!(
+                "input device is {} ({:?})",
+                source.path.display(),
+                device.input_id()
+            );
+            Ok(Source {
+                path: source.path,
+                device,
+                mappings: source.mappings,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-    let mut virt_device = make_virt_device(&device)?;
+    let (mut virt_device, virt_abs_infos) = make_virt_device(&profile.device, &sources)?;
     for path in virt_device.enumerate_dev_nodes_blocking()? {
         println!("virt device available as {}", path?.display());
     }
 
-    let mappings = parse_mappings(&args.mappings).context("")?;
-    let abs_infos = abs_infos(&device)?;
+    let virt_abs_infos = Arc::new(virt_abs_infos);
+    let (tx, rx) = mpsc::channel::<Vec<InputEvent>>();
+    for source in sources {
+        let tx = tx.clone();
+        let path = source.path.clone();
+        let virt_abs_infos = Arc::clone(&virt_abs_infos);
+        thread::spawn(move || {
+            if let Err(err) = run_source(source, &virt_abs_infos, &tx) {
+                eprintln!("source {} exited: {err:?}", path.display());
+            }
+        });
+    }
+    drop(tx);
+
+    for batch in rx {
+        virt_device.emit(&batch)?;
+    }
+    Ok(())
+}
+
+/// Opens `path` if it already exists, otherwise blocks until a matching device shows up under
+/// `/dev/input` (via [`wait_for_device`]). Lets `main` launch before a source is plugged in.
+fn connect(path: &Path) -> Result<Device> {
+    if let Ok(canon) = canonicalize(path) {
+        if let Ok(device) = Device::open(&canon) {
+            return Ok(device);
+        }
+    }
+    println!("waiting for device at {}", path.display());
+    wait_for_device(path, &None)
+}
+
+/// Supervises one physical device for the lifetime of the process: reads its events, maps and
+/// calibrates them, and forwards the result to `tx` so the main thread can multiplex every
+/// source into the single virtual pad. `virt_abs_infos` is the merged, cross-source axis map the
+/// virtual device was actually registered with, so a mapping whose target axis is native to a
+/// different source still calibrates against that axis's real range rather than a default one.
+/// When the device disconnects, neutralizes whatever it was holding and blocks until it (or its
+/// replacement, identified by name and vendor/product rather than its volatile `eventN` node)
+/// reappears, then resumes.
+fn run_source(
+    mut source: Source,
+    virt_abs_infos: &HashMap<AbsoluteAxisCode, AbsInfo>,
+    tx: &mpsc::Sender<Vec<InputEvent>>,
+) -> Result<()> {
+    let mut identity = DeviceIdentity::of(&source.device);
+    loop {
+        let abs_infos = abs_infos(&source.device)?;
+        let mut held_keys = HashSet::<KeyCode>::new();
+        let result = poll_source(&mut source, &abs_infos, virt_abs_infos, &mut held_keys, tx);
+        if let Err(err) = result {
+            eprintln!("source {} disconnected: {err:#}", source.path.display());
+        }
+        neutralize(&source.mappings, &abs_infos, virt_abs_infos, &held_keys, tx)?;
+
+        source.device = wait_for_device(&source.path, &Some(identity.clone()))?;
+        identity = DeviceIdentity::of(&source.device);
+        println!("source {} reconnected", source.path.display());
+    }
+}
+
+/// Reads events from `source.device` until it errors (typically because the device was
+/// unplugged), mapping and calibrating them into `tx`. `abs_infos` describes this source's own
+/// axes (for reading raw values); `virt_abs_infos` describes the virtual device's registered
+/// axes (for calibrating/recentering a mapping's target, which may be native to another source).
+fn poll_source(
+    source: &mut Source,
+    abs_infos: &HashMap<AbsoluteAxisCode, AbsInfo>,
+    virt_abs_infos: &HashMap<AbsoluteAxisCode, AbsInfo>,
+    held_keys: &mut HashSet<KeyCode>,
+    tx: &mpsc::Sender<Vec<InputEvent>>,
+) -> Result<()> {
+    let mut last_abs = HashMap::<u16, i32>::new();
+    let mut rel_abs = HashMap::<u16, i32>::new();
+    let mut dropped = false;
     loop {
-        for ev in device.fetch_events()? {
+        let events = source.device.fetch_events()?.collect::<Vec<_>>();
+        for ev in events {
             match ev.destructure() {
+                Synchronization(_, SynchronizationCode::SYN_DROPPED, _) => dropped = true,
+                Synchronization(_, SynchronizationCode::SYN_REPORT, _) if dropped => {
+                    dropped = false;
+                    resync(
+                        &source.device,
+                        tx,
+                        &source.mappings,
+                        abs_infos,
+                        virt_abs_infos,
+                        &mut last_abs,
+                        held_keys,
+                    )?;
+                }
                 Synchronization(..) => continue,
-                Key(key_event, key_code, _) => {
-                    println!("{:?} {:?}", key_event, key_code)
+                _ if dropped => continue,
+                Key(_, key_code, value) => {
+                    if value == 2 {
+                        continue; // ignore key repeat, buttons don't repeat
+                    }
+                    if let Some(Mapping {
+                        target: MapTarget::Key(target),
+                        ..
+                    }) = source.mappings.get(&MapSource::Key(key_code))
+                    {
+                        if value == 0 {
+                            held_keys.remove(target);
+                        } else {
+                            held_keys.insert(*target);
+                        }
+                        let virt_ev = *KeyEvent::new(*target, value);
+                        tx.send(vec![virt_ev])?;
+                    }
+                }
+                RelativeAxis(_, code, value) => {
+                    if let Some(Mapping {
+                        target: MapTarget::AbsoluteAxis(target),
+                        invert,
+                        ..
+                    }) = source.mappings.get(&MapSource::RelativeAxis(code))
+                    {
+                        let delta = if *invert { -value } else { value };
+                        let info = virt_abs_infos
+                            .get(target)
+                            .cloned()
+                            .unwrap_or(default_abs_info());
+                        let next = rel_abs
+                            .get(&target.0)
+                            .copied()
+                            .unwrap_or_else(|| info.value())
+                            + delta;
+                        let next = next.clamp(info.minimum(), info.maximum());
+                        rel_abs.insert(target.0, next);
+                        let virt_ev = *AbsoluteAxisEvent::new(*target, next);
+                        tx.send(vec![virt_ev])?;
+                    }
                 }
                 AbsoluteAxis(event, code, value) => {
                     if let Some(abs_info) = abs_infos.get(&code) {
                         let pad = " ".repeat(14 * code.0 as usize);
                         println!("{:?} {}{:?} {}", event.event_type(), pad, code, value);
-                        let mapping = mappings
-                            .get(&code)
+                        let mapping = source
+                            .mappings
+                            .get(&MapSource::AbsoluteAxis(code))
                             .copied()
-                            .unwrap_or(Mapping::from_abs(code));
+                            .unwrap_or(Mapping::identity(code));
+                        let MapTarget::AbsoluteAxis(target) = mapping.target else {
+                            continue;
+                        };
                         let value = if mapping.invert {
                             abs_info.minimum() + (abs_info.maximum() - value)
                         } else {
                             value
                         };
-                        let virt_ev =
-                            *AbsoluteAxisEvent::new(AbsoluteAxisCode(mapping.to_code), value);
-                        virt_device.emit(&[virt_ev])?;
+                        let target_info = virt_abs_infos
+                            .get(&target)
+                            .cloned()
+                            .unwrap_or(default_abs_info());
+                        let value = calibrate(value, abs_info, &target_info, mapping.deadzone);
+                        if let Some(&last) = last_abs.get(&target.0) {
+                            if (value - last).abs() < target_info.fuzz() {
+                                continue;
+                            }
+                        }
+                        last_abs.insert(target.0, value);
+                        let virt_ev = *AbsoluteAxisEvent::new(target, value);
+                        tx.send(vec![virt_ev])?;
                     }
                 }
                 _ => {
@@ -89,40 +344,220 @@ fn main() -> Result<()> {
     }
 }
 
-fn make_virt_device(device: &Device) -> Result<VirtualDevice> {
-    let xbox_id = InputId::new(BusType::BUS_USB, 0x45e, 0x28e, 0x101);
-    let xbox_name = "Microsoft X-Box 360 pad";
+/// Releases every key this source was holding and recenters every axis it maps, so a disconnect
+/// can't leave the virtual pad showing stuck input while the physical device is away. Axes in
+/// `abs_infos` (this source's own) that no rule maps are passed through unchanged by
+/// `poll_source`, so they are recentered too. Recenters
+/// against `virt_abs_infos`, the virtual device's registered axis ranges, since a mapped target
+/// axis may be native to a different source than this one. Sent as one batch so a consumer can't
+/// observe some keys released and others still held.
+fn neutralize(
+    mappings: &HashMap<MapSource, Mapping>,
+    abs_infos: &HashMap<AbsoluteAxisCode, AbsInfo>,
+    virt_abs_infos: &HashMap<AbsoluteAxisCode, AbsInfo>,
+    held_keys: &HashSet<KeyCode>,
+    tx: &mpsc::Sender<Vec<InputEvent>>,
+) -> Result<()> {
+    let mut events = Vec::new();
+    for &key in held_keys {
+        events.push(*KeyEvent::new(key, 0));
+    }
+
+    let passthrough = abs_infos
+        .keys()
+        .filter(|code| !mappings.contains_key(&MapSource::AbsoluteAxis(**code)))
+        .map(|code| MapTarget::AbsoluteAxis(*code));
+    let mut recentered = HashSet::new();
+    for target in mappings.values().map(|m| m.target).chain(passthrough) {
+        if let MapTarget::AbsoluteAxis(target) = target {
+            if !recentered.insert(target) {
+                continue;
+            }
+            let info = virt_abs_infos
+                .get(&target)
+                .cloned()
+                .unwrap_or(default_abs_info());
+            let center = (info.maximum() + info.minimum()) / 2;
+            events.push(*AbsoluteAxisEvent::new(target, center));
+        }
+    }
+
+    if !events.is_empty() {
+        tx.send(events)?;
+    }
+    Ok(())
+}
+
+/// Blocks until a device matching `identity` appears under `/dev/input`, then opens and returns
+/// it. Before any device has connected (`identity` is `None`), there is no stable identifier to
+/// match a freshly-created node against, so instead of comparing paths it re-tries opening `path`
+/// itself after every batch of `/dev/input` changes — the only way to correctly pick up a stable
+/// symlink (e.g. `/dev/input/by-id/...`) that doesn't resolve until the device it names exists.
+/// Arms the inotify `CREATE` watch before scanning what is already there, so a device created in
+/// the gap between the scan and the watch can't be missed.
+fn wait_for_device(path: &Path, identity: &Option<DeviceIdentity>) -> Result<Device> {
+    let matches = |candidate: &Path| -> Option<Device> {
+        let device = Device::open(candidate).ok()?;
+        let id = identity.as_ref()?;
+        (DeviceIdentity::of(&device) == *id).then_some(device)
+    };
+    let try_configured_path = || -> Option<Device> {
+        if identity.is_some() {
+            return None;
+        }
+        Device::open(path).ok()
+    };
+
+    let mut inotify = Inotify::init().context("starting inotify")?;
+    inotify
+        .watches()
+        .add(DEV_INPUT, WatchMask::CREATE)
+        .context("watching /dev/input")?;
+
+    if let Some(device) = try_configured_path() {
+        return Ok(device);
+    }
+    for entry in read_dir(DEV_INPUT).context("reading /dev/input")? {
+        if let Some(device) = matches(&entry?.path()) {
+            return Ok(device);
+        }
+    }
+
+    let mut buffer = [0u8; 4096];
+    loop {
+        for event in inotify.read_events_blocking(&mut buffer)? {
+            let Some(name) = event.name else {
+                continue;
+            };
+            if let Some(device) = matches(&Path::new(DEV_INPUT).join(name)) {
+                return Ok(device);
+            }
+        }
+        if let Some(device) = try_configured_path() {
+            return Ok(device);
+        }
+    }
+}
+
+fn default_abs_info() -> AbsInfo {
+    let axis_max = 256;
+    AbsInfo::new(axis_max / 2, 0, axis_max, 0, 0, 1)
+}
+
+/// Re-reads the physical device's authoritative state after a `SYN_DROPPED` and pushes a fresh
+/// batch of events through the mapping layer, sent as a single batch so it reaches the virtual
+/// pad under one trailing `SYN_REPORT` instead of leaving it observable mid-catch-up, so the
+/// virtual pad can't stay stuck on a value from before the buffer overrun. `abs_infos` is this
+/// source's own axes; `virt_abs_infos` is the virtual device's registered axes, used to calibrate
+/// against a mapping's target, which may be native to another source.
+fn resync(
+    device: &Device,
+    tx: &mpsc::Sender<Vec<InputEvent>>,
+    mappings: &HashMap<MapSource, Mapping>,
+    abs_infos: &HashMap<AbsoluteAxisCode, AbsInfo>,
+    virt_abs_infos: &HashMap<AbsoluteAxisCode, AbsInfo>,
+    last_abs: &mut HashMap<u16, i32>,
+    held_keys: &mut HashSet<KeyCode>,
+) -> Result<()> {
+    println!("resyncing after SYN_DROPPED");
+
+    let mut events = Vec::new();
+    for (code, info) in device.get_absinfo()? {
+        if !abs_infos.contains_key(&code) {
+            continue;
+        }
+        let mapping = mappings
+            .get(&MapSource::AbsoluteAxis(code))
+            .copied()
+            .unwrap_or(Mapping::identity(code));
+        let MapTarget::AbsoluteAxis(target) = mapping.target else {
+            continue;
+        };
+        let value = if mapping.invert {
+            info.minimum() + (info.maximum() - info.value())
+        } else {
+            info.value()
+        };
+        let target_info = virt_abs_infos
+            .get(&target)
+            .cloned()
+            .unwrap_or(default_abs_info());
+        let value = calibrate(value, &info, &target_info, mapping.deadzone);
+        last_abs.insert(target.0, value);
+        events.push(*AbsoluteAxisEvent::new(target, value));
+    }
+
+    let mut still_held = HashSet::new();
+    for key in device.get_key_state()?.iter() {
+        if let Some(Mapping {
+            target: MapTarget::Key(target),
+            ..
+        }) = mappings.get(&MapSource::Key(key))
+        {
+            still_held.insert(*target);
+        }
+    }
+    for &target in held_keys.difference(&still_held) {
+        events.push(*KeyEvent::new(target, 0));
+    }
+    for &target in still_held.difference(held_keys) {
+        events.push(*KeyEvent::new(target, 1));
+    }
+    *held_keys = still_held;
+
+    if !events.is_empty() {
+        tx.send(events)?;
+    }
+    Ok(())
+}
+
+/// Builds the single virtual pad that every `Source` feeds, presenting `identity` to the kernel
+/// and unioning the key and absolute-axis capabilities required across all sources. When two
+/// sources map onto the same absolute axis with different calibration, the first source to
+/// declare it wins. Also returns the merged axis map the device was registered with, so callers
+/// can calibrate a mapping's target against its real range even when that axis is native to a
+/// different source than the one producing the event.
+fn make_virt_device(
+    identity: &VirtualIdentity,
+    sources: &[Source],
+) -> Result<(VirtualDevice, HashMap<AbsoluteAxisCode, AbsInfo>)> {
     let mut keys = AttributeSet::<KeyCode>::new();
-    for key in [
-        KeyCode::BTN_SOUTH,
-        KeyCode::BTN_EAST,
-        KeyCode::BTN_NORTH,
-        KeyCode::BTN_WEST,
-        KeyCode::BTN_TL,
-        KeyCode::BTN_TR,
-        KeyCode::BTN_SELECT,
-        KeyCode::BTN_START,
-        KeyCode::BTN_MODE,
-        KeyCode::BTN_THUMBL,
-        KeyCode::BTN_THUMBR,
-    ] {
-        keys.insert(key);
-    }
-    let abs_infos = abs_infos(device)?;
-    let virt_device = VirtualDeviceBuilder::new()?
-        .name(xbox_name)
-        .input_id(xbox_id)
-        .with_keys(&keys)?
-        .with_absolute_axis(&abs_setup(AbsoluteAxisCode::ABS_X, &abs_infos)?)?
-        .with_absolute_axis(&abs_setup(AbsoluteAxisCode::ABS_Y, &abs_infos)?)?
-        .with_absolute_axis(&abs_setup(AbsoluteAxisCode::ABS_Z, &abs_infos)?)?
-        .with_absolute_axis(&abs_setup(AbsoluteAxisCode::ABS_RX, &abs_infos)?)?
-        .with_absolute_axis(&abs_setup(AbsoluteAxisCode::ABS_RY, &abs_infos)?)?
-        .with_absolute_axis(&abs_setup(AbsoluteAxisCode::ABS_RZ, &abs_infos)?)?
-        .with_absolute_axis(&abs_setup(AbsoluteAxisCode::ABS_HAT0X, &abs_infos)?)?
-        .with_absolute_axis(&abs_setup(AbsoluteAxisCode::ABS_HAT0Y, &abs_infos)?)?
-        .build()?;
-    Ok(virt_device)
+    let mut abs_codes = HashSet::<u16>::new();
+    let mut merged_abs_infos = HashMap::<AbsoluteAxisCode, AbsInfo>::new();
+    for source in sources {
+        for mapping in source.mappings.values() {
+            match mapping.target {
+                MapTarget::Key(code) => keys.insert(code),
+                MapTarget::AbsoluteAxis(code) => {
+                    abs_codes.insert(code.0);
+                }
+            }
+        }
+
+        let source_abs_infos = abs_infos(&source.device)?;
+        for code in source_abs_infos.keys() {
+            if !source
+                .mappings
+                .contains_key(&MapSource::AbsoluteAxis(*code))
+            {
+                abs_codes.insert(code.0);
+            }
+        }
+        for (code, info) in source_abs_infos {
+            merged_abs_infos.entry(code).or_insert(info);
+        }
+    }
+
+    let mut builder = VirtualDeviceBuilder::new()?
+        .name(&identity.name)
+        .input_id(identity.input_id.clone())
+        .with_keys(&keys)?;
+    for code in abs_codes {
+        builder =
+            builder.with_absolute_axis(&abs_setup(AbsoluteAxisCode(code), &merged_abs_infos)?)?;
+    }
+    let virt_device = builder.build()?;
+    Ok((virt_device, merged_abs_infos))
 }
 
 fn abs_infos(device: &Device) -> Result<HashMap<AbsoluteAxisCode, AbsInfo>> {
@@ -131,7 +566,14 @@ fn abs_infos(device: &Device) -> Result<HashMap<AbsoluteAxisCode, AbsInfo>> {
         .map(|(code, i)| {
             (
                 code,
-                AbsInfo::new(i.value(), i.minimum(), i.maximum(), 0, 0, i.resolution()),
+                AbsInfo::new(
+                    i.value(),
+                    i.minimum(),
+                    i.maximum(),
+                    i.fuzz(),
+                    i.flat(),
+                    i.resolution(),
+                ),
             )
         })
         .collect::<HashMap<_, _>>())
@@ -141,61 +583,112 @@ fn abs_setup(
     code: AbsoluteAxisCode,
     abs_infos: &HashMap<AbsoluteAxisCode, AbsInfo>,
 ) -> Result<UinputAbsSetup> {
-    let axis_max = 256;
-    let default_info = AbsInfo::new(axis_max / 2, 0, axis_max, 0, 0, 1);
-    let info = abs_infos.get(&code).cloned().unwrap_or(default_info);
+    let info = abs_infos.get(&code).cloned().unwrap_or(default_abs_info());
     Ok(UinputAbsSetup::new(code, info))
 }
 
-fn parse_mapping(input: &str) -> Result<(AbsoluteAxisCode, Mapping)> {
-    match input.split("=").collect::<Vec<_>>()[..] {
+fn parse_source(input: &str) -> Result<MapSource> {
+    if let Ok(code) = AbsoluteAxisCode::from_str(input) {
+        return Ok(MapSource::AbsoluteAxis(code));
+    }
+    if let Ok(code) = RelativeAxisCode::from_str(input) {
+        return Ok(MapSource::RelativeAxis(code));
+    }
+    if let Ok(code) = KeyCode::from_str(input) {
+        return Ok(MapSource::Key(code));
+    }
+    bail!("unknown mapping source {input}")
+}
+
+fn parse_target(input: &str) -> Result<MapTarget> {
+    if let Ok(code) = AbsoluteAxisCode::from_str(input) {
+        return Ok(MapTarget::AbsoluteAxis(code));
+    }
+    if let Ok(code) = KeyCode::from_str(input) {
+        return Ok(MapTarget::Key(code));
+    }
+    bail!("unknown mapping target {input}")
+}
+
+fn parse_mapping(input: &str) -> Result<(MapSource, Mapping)> {
+    let mut parts = input.split(':');
+    let rule = parts.next().context("empty mapping")?;
+
+    let (source, target, invert) = match rule.split("=").collect::<Vec<_>>()[..] {
         [l_op, r_op] => {
-            let l_op = <AbsoluteAxisCode as FromStr>::from_str(l_op)?;
-            let mapping = match r_op.split("-").collect::<Vec<_>>()[..] {
-                [r_op] => {
-                    let r_op = <AbsoluteAxisCode as FromStr>::from_str(r_op)?;
-                    Mapping::from_abs(r_op)
-                }
-                [_, r_op] => {
-                    let r_op = <AbsoluteAxisCode as FromStr>::from_str(r_op)?;
-                    Mapping::from_abs_inv(r_op)
-                }
-                _ => bail!("cannot parse right operand"),
+            let source = parse_source(l_op)?;
+            let (invert, r_op) = match r_op.strip_prefix('-') {
+                Some(rest) => (true, rest),
+                None => (false, r_op),
             };
-            Ok((l_op, mapping))
+            let target = parse_target(r_op)?;
+            (source, target, invert)
         }
         _ => bail!("cannot parse mapping"),
+    };
+
+    let mut deadzone = 0.0;
+    for option in parts {
+        match option.split_once('=') {
+            Some(("dz", v)) => {
+                let parsed: f32 = v.parse().context("invalid deadzone")?;
+                if !(0.0..1.0).contains(&parsed) {
+                    bail!("deadzone must be in [0, 1), got {parsed}");
+                }
+                deadzone = parsed;
+            }
+            _ => bail!("unknown mapping option {option}"),
+        }
     }
+
+    Ok((
+        source,
+        Mapping {
+            target,
+            invert,
+            deadzone,
+        },
+    ))
 }
 
-fn parse_mappings(input: &str) -> Result<HashMap<AbsoluteAxisCode, Mapping>> {
-    let res: HashMap<AbsoluteAxisCode, Mapping> =
+fn parse_mappings(input: &str) -> Result<HashMap<MapSource, Mapping>> {
+    let res: HashMap<MapSource, Mapping> =
         input.split(",").map(parse_mapping).collect::<Result<_>>()?;
     Ok(res)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{parse_mappings, Mapping};
-    use evdev::AbsoluteAxisCode;
+    use crate::{calibrate, parse_mappings, MapSource, MapTarget, Mapping};
+    use evdev::{AbsInfo, AbsoluteAxisCode, KeyCode};
     use std::collections::HashMap;
 
     #[test]
     fn should_parse_mappings() {
-        let input = "ABS_X=-ABS_X,ABS_Z=ABS_RX";
+        let input = "ABS_X=-ABS_X,ABS_Z=ABS_RX:dz=0.12,KEY_SPACE=BTN_SOUTH";
         let expected = [
             (
-                AbsoluteAxisCode::ABS_X,
+                MapSource::AbsoluteAxis(AbsoluteAxisCode::ABS_X),
                 Mapping {
-                    to_code: AbsoluteAxisCode::ABS_X.0,
+                    target: MapTarget::AbsoluteAxis(AbsoluteAxisCode::ABS_X),
                     invert: true,
+                    deadzone: 0.0,
                 },
             ),
             (
-                AbsoluteAxisCode::ABS_Z,
+                MapSource::AbsoluteAxis(AbsoluteAxisCode::ABS_Z),
                 Mapping {
-                    to_code: AbsoluteAxisCode::ABS_RX.0,
+                    target: MapTarget::AbsoluteAxis(AbsoluteAxisCode::ABS_RX),
                     invert: false,
+                    deadzone: 0.12,
+                },
+            ),
+            (
+                MapSource::Key(KeyCode::KEY_SPACE),
+                Mapping {
+                    target: MapTarget::Key(KeyCode::BTN_SOUTH),
+                    invert: false,
+                    deadzone: 0.0,
                 },
             ),
         ]
@@ -204,4 +697,14 @@ mod tests {
 
         assert_eq!(expected, parse_mappings(input).expect("parsing failure"));
     }
+
+    #[test]
+    fn should_apply_deadzone_continuously_at_the_edge() {
+        let info = AbsInfo::new(0, -256, 256, 0, 0, 0);
+        assert_eq!(calibrate(0, &info, &info, 0.5), 0);
+        assert_eq!(calibrate(128, &info, &info, 0.5), 0);
+        assert_eq!(calibrate(129, &info, &info, 0.5), 2);
+        assert_eq!(calibrate(192, &info, &info, 0.5), 128);
+        assert_eq!(calibrate(256, &info, &info, 0.5), 256);
+    }
 }